@@ -0,0 +1,307 @@
+use crate::{
+    default_trigger, Debounce, Error, Recheck, ResizeStream, Target, Trigger,
+    ZeroSizePolicy,
+};
+#[cfg(unix)]
+use crate::{open_terminal, PtyHandle, CONTROLLING_TERMINAL};
+
+enum Source {
+    Stdio,
+    #[cfg(unix)]
+    Fd(std::os::unix::io::RawFd),
+    #[cfg(unix)]
+    Path(std::path::PathBuf),
+    #[cfg(unix)]
+    Pty(std::os::unix::io::RawFd, Trigger),
+    #[cfg(feature = "test-support")]
+    Mock(crate::mock::SharedSize, Trigger),
+}
+
+/// Builder for configuring a `ResizeStream`.
+///
+/// The defaults match the behavior of `resizes()`: the size is read from
+/// stdio, the current size is returned first, duplicate sizes are
+/// suppressed, notifications are not debounced, pixel dimensions are
+/// included, and failures (including sizes of zero) are returned as errors.
+#[must_use = "builders do nothing unless built"]
+// these are independent options, not a state machine
+#[allow(clippy::struct_excessive_bools)]
+pub struct ResizeStreamBuilder {
+    source: Source,
+    initial_size: bool,
+    dedup: bool,
+    debounce: Option<(std::time::Duration, std::time::Duration)>,
+    include_pixels: bool,
+    zero_size: ZeroSizePolicy,
+    skip_errors: bool,
+    fallback_size: bool,
+    poll_interval: Option<std::time::Duration>,
+    poll_fallback: Option<std::time::Duration>,
+    recheck: Option<std::time::Duration>,
+}
+
+impl ResizeStreamBuilder {
+    /// Creates a new builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the terminal size from `fd` rather than from stdio.
+    ///
+    /// The file descriptor is not owned by the resulting stream, so it must
+    /// remain open for as long as the stream is in use.
+    #[cfg(unix)]
+    pub fn fd(mut self, fd: &impl std::os::unix::io::AsRawFd) -> Self {
+        self.source = Source::Fd(fd.as_raw_fd());
+        self
+    }
+
+    /// Reads the terminal size from the terminal at `path` (for instance,
+    /// `/dev/tty`) rather than from stdio.
+    ///
+    /// The terminal is opened by `build`, and closed when the resulting
+    /// stream is dropped.
+    #[cfg(unix)]
+    pub fn path(mut self, path: impl AsRef<std::path::Path>) -> Self {
+        self.source = Source::Path(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Reads the terminal size from the controlling terminal of this
+    /// process (`/dev/tty`) rather than from stdio.
+    ///
+    /// This is useful for processes whose stdio has been redirected (or
+    /// closed) but which still want to follow the user's terminal. As with
+    /// `path`, the terminal is opened by `build`, and closed when the
+    /// resulting stream is dropped. Note that SIGWINCH is only sent to the
+    /// terminal's foreground process group, so a background process should
+    /// also use `poll_interval` or `recheck_after`.
+    #[cfg(unix)]
+    pub fn controlling_terminal(self) -> Self {
+        self.path(CONTROLLING_TERMINAL)
+    }
+
+    /// Follows the size of `pty` rather than the user's terminal. See
+    /// `PtyHandle::resizes` for details.
+    #[cfg(unix)]
+    pub fn pty(mut self, pty: &PtyHandle) -> Self {
+        self.source = Source::Pty(pty.fd(), pty.trigger());
+        self
+    }
+
+    /// Follows the size of `terminal` rather than the user's terminal. See
+    /// `MockTerminal` for details.
+    #[cfg(feature = "test-support")]
+    pub fn mock(mut self, terminal: &crate::mock::MockTerminal) -> Self {
+        self.source =
+            Source::Mock(terminal.shared_size(), terminal.trigger());
+        self
+    }
+
+    /// Sets whether the stream should return the current size before any
+    /// resize notifications are received. Defaults to `true`.
+    ///
+    /// Set this to `false` if the current size has already been measured
+    /// some other way and only subsequent changes are of interest. In that
+    /// case, the size is still measured when the stream is built so that
+    /// `dedup` has something to compare the first change against.
+    pub const fn initial_size(mut self, initial_size: bool) -> Self {
+        self.initial_size = initial_size;
+        self
+    }
+
+    /// Sets whether a resize notification which results in the same size
+    /// as the previously returned size should be suppressed. Defaults to
+    /// `true`.
+    ///
+    /// Terminals often send resize notifications when nothing relevant has
+    /// changed (switching tmux windows, for instance), so this is usually
+    /// what you want.
+    pub const fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Coalesces bursts of resize notifications, such as those generated
+    /// while the user is dragging the edge of a window.
+    ///
+    /// A new size will only be returned once no further notifications have
+    /// been received for `delay`, or once `max_delay` has passed since the
+    /// first notification in the burst, whichever comes first. The initial
+    /// size is always returned immediately. This requires the tokio runtime
+    /// to have the time driver enabled.
+    pub const fn debounce(
+        mut self,
+        delay: std::time::Duration,
+        max_delay: std::time::Duration,
+    ) -> Self {
+        self.debounce = Some((delay, max_delay));
+        self
+    }
+
+    /// Sets whether the returned sizes should include pixel dimensions.
+    /// Defaults to `true`.
+    ///
+    /// When this is `false`, the `pixel_width` and `pixel_height` fields
+    /// will always be `None`, and so changes which only affect the pixel
+    /// dimensions (such as changing the font size without changing the
+    /// number of rows or columns) will be suppressed by `dedup`.
+    pub const fn include_pixels(mut self, include_pixels: bool) -> Self {
+        self.include_pixels = include_pixels;
+        self
+    }
+
+    /// Sets what the stream should do when the terminal reports a size of
+    /// zero rows or columns. Defaults to `ZeroSizePolicy::Error`.
+    ///
+    /// A fallback size is subject to `dedup` like any other size, so with
+    /// `ZeroSizePolicy::Fallback`, a terminal which keeps reporting a size
+    /// of zero will only return the fallback size once.
+    pub const fn zero_size(mut self, policy: ZeroSizePolicy) -> Self {
+        self.zero_size = policy;
+        self
+    }
+
+    /// Sets whether failures to get the terminal size should be skipped
+    /// rather than returned. Defaults to `false`.
+    ///
+    /// Errors never end the stream, but they are easy to mistake for fatal
+    /// errors when using combinators like `try_for_each`. When this is
+    /// `true`, the stream only ever returns `Ok` items, and waits for the
+    /// next resize notification after a failure. Sizes of zero are only
+    /// skipped if `zero_size` is left as `ZeroSizePolicy::Error`.
+    pub const fn skip_errors(mut self, skip_errors: bool) -> Self {
+        self.skip_errors = skip_errors;
+        self
+    }
+
+    /// Sets whether the initial size should fall back to a guess if it
+    /// can't be measured, rather than returning an error. Defaults to
+    /// `false`.
+    ///
+    /// The fallback is the same as for `current_size_or_fallback`: the
+    /// `LINES` and `COLUMNS` environment variables, and then 80x24.
+    /// `ResizeStream::source` (or `ResizeEvent::source`) can be used to tell
+    /// whether the size was measured. Failures after the initial size are
+    /// returned as usual. This has no effect if `initial_size` is `false`.
+    pub const fn fallback_size(mut self, fallback_size: bool) -> Self {
+        self.fallback_size = fallback_size;
+        self
+    }
+
+    /// Checks the terminal size every `interval` rather than waiting for
+    /// resize notifications.
+    ///
+    /// This is useful when resize notifications are unavailable, for
+    /// instance when the process isn't in the terminal's foreground process
+    /// group, or is run under a supervisor which doesn't forward signals.
+    /// Only sizes which differ from the previously returned size are
+    /// returned, regardless of `dedup`. This requires the tokio runtime to
+    /// have the time driver enabled. This has no effect when combined with
+    /// `pty` or `mock`. Intervals shorter than 1ms (including zero) are
+    /// treated as 1ms.
+    pub const fn poll_interval(
+        mut self,
+        interval: std::time::Duration,
+    ) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Falls back to checking the terminal size every `interval` (as with
+    /// `poll_interval`) if registering for resize notifications fails,
+    /// rather than returning an error from `build`. As with
+    /// `poll_interval`, intervals shorter than 1ms are treated as 1ms.
+    pub const fn poll_fallback(
+        mut self,
+        interval: std::time::Duration,
+    ) -> Self {
+        self.poll_fallback = Some(interval);
+        self
+    }
+
+    /// Checks the terminal size again `delay` after each resize
+    /// notification, returning the new size if it differs from the
+    /// previously returned size.
+    ///
+    /// Resize notifications can be coalesced, and the final notification of
+    /// a burst may arrive before the new size is visible, which would
+    /// otherwise leave the stream reporting a stale size until the next
+    /// notification. This requires the tokio runtime to have the time
+    /// driver enabled.
+    pub const fn recheck_after(mut self, delay: std::time::Duration) -> Self {
+        self.recheck = Some(delay);
+        self
+    }
+
+    /// Creates the configured stream. Unless `pty` or `mock` was used, this
+    /// must be called from within the context of a tokio runtime.
+    ///
+    /// # Errors
+    /// * `Error::OpenTerminal`: failed to open the terminal given to `path`,
+    ///   or this process has no controlling terminal and
+    ///   `controlling_terminal` was used
+    /// * `Error::SigWinchHandler`: failed to register a SIGWINCH handler (and
+    ///   `poll_fallback` was not used)
+    pub fn build(self) -> Result<ResizeStream, Error> {
+        let (target, trigger) = match self.source {
+            Source::Stdio => (Target::Stdio, self.trigger()?),
+            #[cfg(unix)]
+            Source::Fd(fd) => (Target::Fd(fd), self.trigger()?),
+            #[cfg(unix)]
+            Source::Path(ref path) => {
+                (Target::File(open_terminal(path)?), self.trigger()?)
+            }
+            #[cfg(unix)]
+            Source::Pty(fd, trigger) => (Target::Fd(fd), trigger),
+            #[cfg(feature = "test-support")]
+            Source::Mock(size, trigger) => (Target::Mock(size), trigger),
+        };
+        let mut stream = ResizeStream::new(target, trigger);
+        stream.dedup = self.dedup;
+        stream.include_pixels = self.include_pixels;
+        stream.zero_size = self.zero_size;
+        stream.skip_errors = self.skip_errors;
+        stream.fallback_size = self.fallback_size;
+        stream.debounce = self
+            .debounce
+            .map(|(delay, max_delay)| Debounce::new(delay, max_delay));
+        stream.recheck = self.recheck.map(Recheck::new);
+        if !self.initial_size {
+            stream.sent_initial_size = true;
+            if let Ok((size, source)) = stream.size() {
+                stream.last_size = Some(size);
+                stream.source = source;
+            }
+        }
+        Ok(stream)
+    }
+
+    fn trigger(&self) -> Result<Trigger, Error> {
+        if let Some(interval) = self.poll_interval {
+            return Ok(Trigger::poll(interval));
+        }
+        match default_trigger() {
+            Ok(trigger) => Ok(trigger),
+            Err(e) => self.poll_fallback.map(Trigger::poll).ok_or(e),
+        }
+    }
+}
+
+impl Default for ResizeStreamBuilder {
+    fn default() -> Self {
+        Self {
+            source: Source::Stdio,
+            initial_size: true,
+            dedup: true,
+            debounce: None,
+            include_pixels: true,
+            zero_size: ZeroSizePolicy::Error,
+            skip_errors: false,
+            fallback_size: false,
+            poll_interval: None,
+            poll_fallback: None,
+            recheck: None,
+        }
+    }
+}
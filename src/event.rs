@@ -0,0 +1,116 @@
+use crate::{Error, ResizeStream, SizeSource, TerminalSize};
+use futures::stream::StreamExt as _;
+
+/// A change in the size of a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResizeEvent {
+    /// The previously returned size, or `None` if there is no previous size
+    ///
+    /// This is usually because the event reports the initial size. If the
+    /// stream had already returned a size before being converted with
+    /// `ResizeStream::events`, the first event will have a previous size.
+    /// The same is true for a stream built with `initial_size(false)`, but
+    /// only if the size could be measured when the stream was built.
+    pub old: Option<TerminalSize>,
+
+    /// The new size
+    pub new: TerminalSize,
+
+    /// The time at which the resize notification which led to this event
+    /// was received
+    ///
+    /// When debouncing, this is the time of the last notification in the
+    /// burst. For the first event, this is the time the stream was first
+    /// polled, and for events produced by a recheck (see
+    /// `ResizeStreamBuilder::recheck_after`), this is the time of the
+    /// recheck.
+    pub time: std::time::Instant,
+
+    /// The position of this event in the stream, starting from zero
+    ///
+    /// Events are numbered consecutively, so a gap in the sequence numbers
+    /// seen by a consumer indicates that events were dropped somewhere
+    /// between the stream and the consumer.
+    pub seq: u64,
+
+    /// Where the new size came from
+    pub source: SizeSource,
+}
+
+impl ResizeEvent {
+    /// Returns true if there is no previous size, which usually means that
+    /// this event reports the initial size. See `old` for details.
+    #[must_use]
+    pub const fn is_initial(&self) -> bool {
+        self.old.is_none()
+    }
+
+    /// Returns true if the terminal now has more rows than it did before.
+    #[must_use]
+    pub fn grew_rows(&self) -> bool {
+        self.old.is_some_and(|old| self.new.rows > old.rows)
+    }
+
+    /// Returns true if the terminal now has fewer rows than it did before.
+    #[must_use]
+    pub fn shrank_rows(&self) -> bool {
+        self.old.is_some_and(|old| self.new.rows < old.rows)
+    }
+
+    /// Returns true if the terminal now has more columns than it did
+    /// before.
+    #[must_use]
+    pub fn grew_cols(&self) -> bool {
+        self.old.is_some_and(|old| self.new.cols > old.cols)
+    }
+
+    /// Returns true if the terminal now has fewer columns than it did
+    /// before.
+    #[must_use]
+    pub fn shrank_cols(&self) -> bool {
+        self.old.is_some_and(|old| self.new.cols < old.cols)
+    }
+}
+
+/// Stream which returns a `ResizeEvent` every time the terminal size
+/// changes. Created by `ResizeStream::events`.
+#[must_use = "streams do nothing unless polled"]
+pub struct ResizeEvents {
+    stream: ResizeStream,
+    last_size: Option<TerminalSize>,
+    next_seq: u64,
+}
+
+impl ResizeEvents {
+    pub(crate) const fn new(stream: ResizeStream) -> Self {
+        Self {
+            last_size: stream.last_size,
+            stream,
+            next_seq: 0,
+        }
+    }
+}
+
+impl futures::stream::Stream for ResizeEvents {
+    type Item = Result<ResizeEvent, Error>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let size = futures::ready!(self.stream.poll_next_unpin(cx));
+        std::task::Poll::Ready(size.map(|size| {
+            let new = size?;
+            let old = self.last_size.replace(new);
+            let seq = self.next_seq;
+            self.next_seq += 1;
+            Ok(ResizeEvent {
+                old,
+                new,
+                time: self.stream.notified_at,
+                seq,
+                source: self.stream.source(),
+            })
+        }))
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{ResizeStream, TerminalSize};
+use futures::stream::StreamExt as _;
+
+/// Shares a single `ResizeStream` between many subscribers.
+///
+/// Each call to `resizes` registers a new signal handler and queries the
+/// terminal size independently, which is wasteful when many components of
+/// an application are interested in the terminal size. A hub drives a
+/// single stream in a background task and distributes the latest size to
+/// each of its subscribers.
+///
+/// Errors returned by the underlying stream are not distributed to
+/// subscribers (since they can't be cloned), and so are skipped.
+#[derive(Clone)]
+pub struct ResizeHub {
+    rx: tokio::sync::watch::Receiver<Option<TerminalSize>>,
+}
+
+impl ResizeHub {
+    /// Creates a new hub which distributes the sizes returned by `stream`.
+    ///
+    /// This spawns a task onto the current tokio runtime, and so must be
+    /// called from within the context of a tokio runtime. The task runs
+    /// until the stream ends or until the hub and all of its subscribers
+    /// have been dropped.
+    #[must_use]
+    pub fn new(stream: ResizeStream) -> Self {
+        let (tx, rx) = tokio::sync::watch::channel(None);
+        tokio::spawn(run(stream, tx));
+        Self { rx }
+    }
+
+    /// Creates a new subscriber, which will first receive the latest size
+    /// (if one has been received yet), followed by each subsequent size.
+    pub fn subscribe(&self) -> ResizeSubscriber {
+        ResizeSubscriber::new(self.rx.clone())
+    }
+
+    /// Creates a new handle for reading the latest size on demand.
+    #[must_use]
+    pub fn watch(&self) -> SizeWatch {
+        let mut rx = self.rx.clone();
+        // sizes received before the handle was created aren't new to it
+        rx.borrow_and_update();
+        SizeWatch { rx }
+    }
+}
+
+/// Handle for reading the latest terminal size from a `ResizeHub` on
+/// demand, rather than as a stream.
+///
+/// This is useful for components which only need to know the terminal size
+/// when they render, without running a task of their own to keep track of
+/// it.
+#[derive(Clone)]
+pub struct SizeWatch {
+    rx: tokio::sync::watch::Receiver<Option<TerminalSize>>,
+}
+
+impl SizeWatch {
+    /// Creates a new handle which tracks the sizes returned by `stream`.
+    ///
+    /// This is equivalent to `ResizeHub::new(stream).watch()`, and so must
+    /// also be called from within the context of a tokio runtime.
+    #[must_use]
+    pub fn new(stream: ResizeStream) -> Self {
+        ResizeHub::new(stream).watch()
+    }
+
+    /// Returns the latest size, or `None` if no size has been received
+    /// yet. This doesn't block, and only involves taking a read lock.
+    #[must_use]
+    pub fn get(&self) -> Option<TerminalSize> {
+        *self.rx.borrow()
+    }
+
+    /// Waits until a new size is received, and returns it. Returns `None`
+    /// once the underlying stream has ended.
+    ///
+    /// Any size received since the last call to `changed` (or since this
+    /// handle was created, for the first call) counts as new, so this
+    /// returns immediately if the size changed while the caller was busy.
+    pub async fn changed(&mut self) -> Option<TerminalSize> {
+        loop {
+            self.rx.changed().await.ok()?;
+            let size = *self.rx.borrow_and_update();
+            if size.is_some() {
+                return size;
+            }
+        }
+    }
+}
+
+/// Stream which returns the latest terminal size from a `ResizeHub`.
+///
+/// A subscriber which isn't polled as often as the terminal is resized will
+/// skip intermediate sizes, only ever returning the latest one. Cloning a
+/// subscriber creates a new subscriber which starts from the latest size.
+#[must_use = "streams do nothing unless polled"]
+pub struct ResizeSubscriber {
+    rx: tokio::sync::watch::Receiver<Option<TerminalSize>>,
+    stream: tokio_stream::wrappers::WatchStream<Option<TerminalSize>>,
+}
+
+impl ResizeSubscriber {
+    fn new(rx: tokio::sync::watch::Receiver<Option<TerminalSize>>) -> Self {
+        let stream = tokio_stream::wrappers::WatchStream::new(rx.clone());
+        Self { rx, stream }
+    }
+}
+
+impl Clone for ResizeSubscriber {
+    fn clone(&self) -> Self {
+        Self::new(self.rx.clone())
+    }
+}
+
+impl futures::stream::Stream for ResizeSubscriber {
+    type Item = TerminalSize;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        loop {
+            match futures::ready!(self.stream.poll_next_unpin(cx)) {
+                Some(Some(size)) => {
+                    return std::task::Poll::Ready(Some(size));
+                }
+                // no size has been received yet
+                Some(None) => {}
+                None => return std::task::Poll::Ready(None),
+            }
+        }
+    }
+}
+
+async fn run(
+    mut stream: ResizeStream,
+    tx: tokio::sync::watch::Sender<Option<TerminalSize>>,
+) {
+    loop {
+        let closed = tx.closed();
+        futures::pin_mut!(closed);
+        match futures::future::select(stream.next(), closed).await {
+            futures::future::Either::Left((Some(Ok(size)), _)) => {
+                tx.send_replace(Some(size));
+            }
+            futures::future::Either::Left((Some(Err(_)), _)) => {}
+            futures::future::Either::Left((None, _))
+            | futures::future::Either::Right(_) => break,
+        }
+    }
+}
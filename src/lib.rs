@@ -8,17 +8,16 @@
 //!
 //! # Synopsis
 //!
-//! ```
-//! # use futures::future::Future as _;
-//! # use futures::stream::Stream as _;
-//! let stream = tokio_terminal_resize::resizes().flatten_stream();
-//! let prog = stream
-//!     .for_each(|(rows, cols)| {
-//!         println!("terminal is now {}x{}", cols, rows);
-//!         Ok(())
-//!     })
-//!     .map_err(|e| eprintln!("error: {}", e));
-//! tokio::run(prog);
+//! ```no_run
+//! # use futures::stream::StreamExt as _;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), tokio_terminal_resize::Error> {
+//! let mut stream = tokio_terminal_resize::resizes()?;
+//! while let Some(size) = stream.next().await {
+//!     println!("terminal is now {}", size?);
+//! }
+//! # Ok(())
+//! # }
 //! ```
 
 // XXX this is broken with ale
@@ -27,102 +26,851 @@
 #![warn(clippy::nursery)]
 #![allow(clippy::multiple_crate_versions)]
 
-use futures::future::Future as _;
-use futures::stream::Stream as _;
-use snafu::futures01::FutureExt as _;
-use snafu::futures01::StreamExt as _;
+use futures::future::FutureExt as _;
+#[cfg(unix)]
+use futures::stream::StreamExt as _;
+#[cfg(unix)]
 use snafu::ResultExt as _;
-use std::convert::TryInto as _;
+
+mod builder;
+pub use builder::ResizeStreamBuilder;
+mod event;
+pub use event::{ResizeEvent, ResizeEvents};
+mod hub;
+pub use hub::{ResizeHub, ResizeSubscriber, SizeWatch};
+#[cfg(feature = "test-support")]
+pub mod mock;
+#[cfg(unix)]
+mod pty;
+#[cfg(unix)]
+pub use pty::PtyHandle;
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+use unix::{
+    default_trigger, open_terminal, set_winsize, Target, CONTROLLING_TERMINAL,
+};
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use windows::{default_trigger, Target};
 
 /// Errors returned by this crate.
 #[derive(Debug, snafu::Snafu)]
 pub enum Error {
+    /// not a terminal
+    #[snafu(display("not a terminal{}", fd_suffix(*fd)))]
+    NotATerminal { fd: Option<std::os::raw::c_int> },
+
     /// failed to get terminal size
-    #[snafu(display("failed to get terminal size"))]
-    GetTerminalSize,
+    #[snafu(display(
+        "failed to get terminal size{}: {}",
+        fd_suffix(*fd),
+        source
+    ))]
+    GetTerminalSize {
+        fd: Option<std::os::raw::c_int>,
+        source: std::io::Error,
+    },
+
+    /// terminal size is zero
+    #[snafu(display(
+        "terminal size is zero ({}x{}){}",
+        cols,
+        rows,
+        fd_suffix(*fd)
+    ))]
+    ZeroTerminalSize {
+        fd: Option<std::os::raw::c_int>,
+        rows: u16,
+        cols: u16,
+    },
+
+    /// failed to open terminal
+    #[cfg(unix)]
+    #[snafu(display("failed to open terminal {}: {}", path.display(), source))]
+    OpenTerminal {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
 
-    /// invalid terminal size found
-    #[snafu(display("invalid terminal size found: {}", source))]
-    InvalidTerminalSize { source: std::num::TryFromIntError },
+    /// failed to set terminal size
+    #[cfg(unix)]
+    #[snafu(display("failed to set terminal size: {}", source))]
+    SetTerminalSize { source: std::io::Error },
+
+    /// failed to forward terminal size
+    #[cfg(unix)]
+    #[snafu(display(
+        "failed to forward terminal size to fd {}: {}",
+        fd,
+        source
+    ))]
+    ForwardTerminalSize {
+        fd: std::os::unix::io::RawFd,
+        source: std::io::Error,
+    },
 
     /// SIGWINCH handler failed
+    #[cfg(unix)]
     #[snafu(display("SIGWINCH handler failed: {}", source))]
     SigWinchHandler { source: std::io::Error },
 }
 
-/// Creates a stream which receives the new terminal size every time the
-/// user's terminal is resized.
-pub fn resizes() -> ResizeFuture {
-    ResizeFuture::default()
-}
-
-/// Future which sets up the terminal size stream
-pub struct ResizeFuture {
-    stream_fut: Box<
-        dyn futures::future::Future<Item = ResizeStream, Error = Error>
-            + Send,
-    >,
-}
-
-impl Default for ResizeFuture {
-    fn default() -> Self {
-        let stream_fut = tokio_signal::unix::Signal::new(
-            tokio_signal::unix::libc::SIGWINCH,
-        )
-        .context(SigWinchHandler)
-        .and_then(|stream| {
-            futures::future::ok(ResizeStream {
-                winches: Box::new(
-                    stream.map(|_| ()).context(SigWinchHandler),
-                ),
-                sent_initial_size: false,
-            })
-        });
+impl Error {
+    /// Returns true if this error was caused by the file descriptor not
+    /// referring to a terminal at all (for instance, because it was
+    /// redirected to a file), as opposed to failing to query a terminal.
+    ///
+    /// This is the case where falling back to a default size is usually
+    /// appropriate.
+    #[must_use]
+    pub const fn is_not_a_terminal(&self) -> bool {
+        matches!(self, Self::NotATerminal { .. })
+    }
+}
+
+fn fd_suffix(fd: Option<std::os::raw::c_int>) -> String {
+    fd.map_or_else(String::new, |fd| format!(" (fd {fd})"))
+}
+
+// returns the first success, or if there were none, the most relevant
+// error: an actual failure to query a terminal is more interesting than the
+// common case of stdio being redirected
+fn first_ok<T>(
+    results: impl IntoIterator<Item = Result<T, Error>>,
+) -> Result<T, Error> {
+    let mut err: Option<Error> = None;
+    for res in results {
+        match res {
+            Ok(t) => return Ok(t),
+            Err(e) => {
+                if err.as_ref().is_none_or(|err| {
+                    err.is_not_a_terminal() && !e.is_not_a_terminal()
+                }) {
+                    err = Some(e);
+                }
+            }
+        }
+    }
+    Err(err.expect("at least one result should be given"))
+}
+
+/// The size of a terminal.
+///
+/// This can be converted to and from a `(rows, cols)` tuple, but using the
+/// named fields is preferred, since other libraries disagree about the order
+/// of the axes. The `Display` implementation uses the conventional
+/// `{cols}x{rows}` form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TerminalSize {
+    /// Number of rows (lines) in the terminal
+    pub rows: u16,
+
+    /// Number of columns in the terminal
+    pub cols: u16,
+
+    /// Width of the terminal in pixels, if the terminal reports it
+    pub pixel_width: Option<u16>,
+
+    /// Height of the terminal in pixels, if the terminal reports it
+    pub pixel_height: Option<u16>,
+}
+
+impl TerminalSize {
+    /// Creates a new `TerminalSize` with the given number of rows and
+    /// columns, and no pixel dimensions.
+    #[must_use]
+    pub const fn new(rows: u16, cols: u16) -> Self {
         Self {
-            stream_fut: Box::new(stream_fut),
+            rows,
+            cols,
+            pixel_width: None,
+            pixel_height: None,
+        }
+    }
+
+    // the windows console reports the visible window as a rectangle with
+    // inclusive bounds
+    #[cfg_attr(not(windows), allow(dead_code))]
+    fn from_console_window(
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16,
+    ) -> Self {
+        let len = |start, end| {
+            let len = i32::from(end) - i32::from(start) + 1;
+            std::convert::TryFrom::try_from(len.max(0)).unwrap_or(u16::MAX)
+        };
+        Self::new(len(top, bottom), len(left, right))
+    }
+
+    const fn check(
+        self,
+        fd: Option<std::os::raw::c_int>,
+    ) -> Result<Self, Error> {
+        if self.rows == 0 || self.cols == 0 {
+            return Err(Error::ZeroTerminalSize {
+                fd,
+                rows: self.rows,
+                cols: self.cols,
+            });
         }
+        Ok(self)
     }
 }
 
-#[must_use = "streams do nothing unless polled"]
-impl futures::future::Future for ResizeFuture {
-    type Item = ResizeStream;
-    type Error = Error;
+impl std::fmt::Display for TerminalSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}x{}", self.cols, self.rows)
+    }
+}
 
-    fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
-        self.stream_fut.poll()
+impl From<(u16, u16)> for TerminalSize {
+    /// Converts from a `(rows, cols)` tuple.
+    fn from((rows, cols): (u16, u16)) -> Self {
+        Self::new(rows, cols)
     }
 }
 
+impl From<TerminalSize> for (u16, u16) {
+    /// Converts to a `(rows, cols)` tuple.
+    fn from(size: TerminalSize) -> Self {
+        (size.rows, size.cols)
+    }
+}
+
+/// What a `ResizeStream` should do when the terminal reports a size of zero
+/// rows or columns.
+///
+/// This happens with some serial consoles and CI environments, and with
+/// newly created PTYs which haven't had a size set yet. See
+/// `ResizeStreamBuilder::zero_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZeroSizePolicy {
+    /// Return `Error::ZeroTerminalSize`. The stream continues afterwards.
+    #[default]
+    Error,
+
+    /// Don't return anything, and wait for the next resize notification.
+    Skip,
+
+    /// Return the given size instead.
+    Fallback(TerminalSize),
+}
+
+/// Where a terminal size came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SizeSource {
+    /// The size was measured from the terminal itself
+    Terminal,
+
+    /// The size was read from the `LINES` and `COLUMNS` environment
+    /// variables
+    Environment,
+
+    /// The size is a default which has no relation to any actual terminal:
+    /// either 80x24, or the size given to `ZeroSizePolicy::Fallback`
+    Default,
+}
+
+const DEFAULT_SIZE: TerminalSize = TerminalSize::new(24, 80);
+
+/// Returns the current size of the user's terminal.
+///
+/// The size is read from the first of stdout, stdin, or stderr which is
+/// connected to a terminal, in the same way as `resizes`.
+///
+/// # Errors
+/// * `Error::NotATerminal`: none of stdout, stdin, or stderr is a terminal
+/// * `Error::GetTerminalSize`: failed to get the terminal size
+/// * `Error::ZeroTerminalSize`: the terminal reported a size of zero
+pub fn current_size() -> Result<TerminalSize, Error> {
+    Target::Stdio.size()
+}
+
+/// Returns the current size of the user's terminal, falling back to a guess
+/// if it can't be measured.
+///
+/// If `current_size` fails (for instance, because there is no terminal at
+/// all), the size is taken from the `LINES` and `COLUMNS` environment
+/// variables, and then from a default of 80x24. If only one of `LINES` and
+/// `COLUMNS` is set, the other dimension comes from the default. The
+/// returned `SizeSource` indicates which of these was used.
+#[must_use]
+pub fn current_size_or_fallback() -> (TerminalSize, SizeSource) {
+    current_size()
+        .map_or_else(|_| fallback_size(), |size| (size, SizeSource::Terminal))
+}
+
+fn fallback_size() -> (TerminalSize, SizeSource) {
+    let lines = std::env::var("LINES").ok();
+    let columns = std::env::var("COLUMNS").ok();
+    env_size(lines.as_deref(), columns.as_deref())
+        .map_or((DEFAULT_SIZE, SizeSource::Default), |size| {
+            (size, SizeSource::Environment)
+        })
+}
+
+fn env_size(
+    lines: Option<&str>,
+    columns: Option<&str>,
+) -> Option<TerminalSize> {
+    let parse = |var: Option<&str>| {
+        var.and_then(|var| var.trim().parse::<u16>().ok())
+            .filter(|&n| n > 0)
+    };
+    match (parse(lines), parse(columns)) {
+        (None, None) => None,
+        (rows, cols) => Some(TerminalSize::new(
+            rows.unwrap_or(DEFAULT_SIZE.rows),
+            cols.unwrap_or(DEFAULT_SIZE.cols),
+        )),
+    }
+}
+
+/// Returns the current size of the terminal referred to by `fd`.
+///
+/// # Errors
+/// * `Error::NotATerminal`: `fd` is not a terminal
+/// * `Error::GetTerminalSize`: failed to get the terminal size
+/// * `Error::ZeroTerminalSize`: the terminal reported a size of zero
+#[cfg(unix)]
+pub fn current_size_for_fd(
+    fd: &impl std::os::unix::io::AsRawFd,
+) -> Result<TerminalSize, Error> {
+    Target::Fd(fd.as_raw_fd()).size()
+}
+
+/// Creates a stream which receives the new terminal size every time the
+/// user's terminal is resized.
+///
+/// The size is read from the first of stdout, stdin, or stderr which is
+/// connected to a terminal. This must be called from within the context of
+/// a tokio runtime.
+///
+/// On Windows, the console provides no resize notifications which don't
+/// interfere with reading input, so the console size is polled instead
+/// (which requires the tokio runtime to have the time driver enabled).
+///
+/// # Errors
+/// * `Error::SigWinchHandler`: failed to register a SIGWINCH handler
+pub fn resizes() -> Result<ResizeStream, Error> {
+    ResizeStream::builder().build()
+}
+
+/// Creates a stream which receives the new terminal size every time the
+/// terminal referred to by `fd` is resized.
+///
+/// This is useful when stdio may be redirected, but some other file
+/// descriptor is known to refer to the terminal. The file descriptor is not
+/// owned by the returned stream, so it must remain open for as long as the
+/// stream is in use. This must be called from within the context of a tokio
+/// runtime.
+///
+/// # Errors
+/// * `Error::SigWinchHandler`: failed to register a SIGWINCH handler
+#[cfg(unix)]
+pub fn resizes_for_fd(
+    fd: &impl std::os::unix::io::AsRawFd,
+) -> Result<ResizeStream, Error> {
+    ResizeStream::builder().fd(fd).build()
+}
+
+/// Creates a stream which receives the new terminal size every time the
+/// terminal at `path` (for instance, `/dev/tty`) is resized.
+///
+/// The terminal is opened when this function is called, and closed when the
+/// returned stream is dropped. This must be called from within the context
+/// of a tokio runtime.
+///
+/// # Errors
+/// * `Error::OpenTerminal`: failed to open `path`
+/// * `Error::SigWinchHandler`: failed to register a SIGWINCH handler
+#[cfg(unix)]
+pub fn resizes_for_path(
+    path: impl AsRef<std::path::Path>,
+) -> Result<ResizeStream, Error> {
+    ResizeStream::builder().path(path).build()
+}
+
+/// Creates a stream which receives the new terminal size every time the
+/// controlling terminal of this process is resized, even if none of stdin,
+/// stdout, or stderr refer to it.
+///
+/// See `ResizeStreamBuilder::controlling_terminal` for details. This must be
+/// called from within the context of a tokio runtime.
+///
+/// # Errors
+/// * `Error::OpenTerminal`: this process has no controlling terminal
+/// * `Error::SigWinchHandler`: failed to register a SIGWINCH handler
+#[cfg(unix)]
+pub fn resizes_for_controlling_terminal() -> Result<ResizeStream, Error> {
+    ResizeStream::builder().controlling_terminal().build()
+}
+
+/// Creates a future which applies the new terminal size to the terminal
+/// referred to by `fd` (typically the master side of a child process's PTY)
+/// every time the user's terminal is resized.
+///
+/// This is equivalent to `resizes()?.forward_to(vec![fd.as_raw_fd()])`,
+/// and so must also be called from within the context of a tokio runtime.
+///
+/// # Errors
+/// * `Error::SigWinchHandler`: failed to register a SIGWINCH handler
+#[cfg(unix)]
+pub fn forward_resizes_to(
+    fd: &impl std::os::unix::io::AsRawFd,
+) -> Result<impl std::future::Future<Output = Result<(), Error>>, Error> {
+    Ok(resizes()?.forward_to(vec![fd.as_raw_fd()]))
+}
+
 /// Stream which returns the new terminal size every time it changes
+///
+/// Failing to get the terminal size (for instance, while a tmux client is
+/// detached) doesn't end the stream: the error is returned, and the size
+/// will be checked again on the next resize notification. Be careful not to
+/// use combinators like `try_for_each` which stop at the first error unless
+/// that is what you want, or use `ResizeStreamBuilder::skip_errors`.
+#[must_use = "streams do nothing unless polled"]
+// these are mostly independent configuration options, not a state machine
+#[allow(clippy::struct_excessive_bools)]
 pub struct ResizeStream {
-    winches:
-        Box<dyn futures::stream::Stream<Item = (), Error = Error> + Send>,
+    target: Target,
+    trigger: Trigger,
     sent_initial_size: bool,
+    dedup: bool,
+    include_pixels: bool,
+    zero_size: ZeroSizePolicy,
+    skip_errors: bool,
+    fallback_size: bool,
+    last_size: Option<TerminalSize>,
+    source: SizeSource,
+    debounce: Option<Debounce>,
+    recheck: Option<Recheck>,
+    notified_at: std::time::Instant,
+}
+
+impl ResizeStream {
+    /// Creates a builder which can be used to configure the behavior of a
+    /// new stream.
+    pub fn builder() -> ResizeStreamBuilder {
+        ResizeStreamBuilder::new()
+    }
+
+    fn new(target: Target, trigger: Trigger) -> Self {
+        Self {
+            target,
+            trigger,
+            sent_initial_size: false,
+            dedup: true,
+            include_pixels: true,
+            zero_size: ZeroSizePolicy::Error,
+            skip_errors: false,
+            fallback_size: false,
+            last_size: None,
+            source: SizeSource::Terminal,
+            debounce: None,
+            recheck: None,
+            notified_at: std::time::Instant::now(),
+        }
+    }
+
+    /// Converts this stream into a stream of `ResizeEvent`s, which include
+    /// the previous size, the time the resize notification was received,
+    /// and a sequence number, along with the new size.
+    pub const fn events(self) -> ResizeEvents {
+        ResizeEvents::new(self)
+    }
+
+    /// Consumes the stream, applying each new size to all of the terminals
+    /// referred to by `fds`.
+    ///
+    /// The returned future resolves once the stream ends. The file
+    /// descriptors are not owned by the future, so they must remain open for
+    /// as long as it is in use. Failures to get the new terminal size are
+    /// skipped (the size will be checked again on the next resize
+    /// notification), rather than ending the future.
+    ///
+    /// # Errors
+    /// * `Error::ForwardTerminalSize`: failed to set the size of one of the
+    ///   terminals in `fds`
+    #[cfg(unix)]
+    pub async fn forward_to(
+        mut self,
+        fds: Vec<std::os::unix::io::RawFd>,
+    ) -> Result<(), Error> {
+        while let Some(size) = self.next().await {
+            let Ok(size) = size else { continue };
+            for &fd in &fds {
+                set_winsize(fd, size).context(ForwardTerminalSize { fd })?;
+            }
+        }
+        Ok(())
+    }
 }
 
-#[must_use = "streams do nothing unless polled"]
 impl futures::stream::Stream for ResizeStream {
-    type Item = (u16, u16);
-    type Error = Error;
+    type Item = Result<TerminalSize, Error>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        loop {
+            let mut initial = false;
+            let mut rechecking = false;
+            if self.sent_initial_size {
+                match self.poll_trigger(cx) {
+                    std::task::Poll::Ready(true) => {
+                        if let Some(recheck) = &mut self.recheck {
+                            recheck.arm();
+                        }
+                    }
+                    std::task::Poll::Ready(false) => {
+                        return std::task::Poll::Ready(None);
+                    }
+                    std::task::Poll::Pending => {
+                        // a recheck during a debounced burst would return
+                        // the size from the middle of it. the recheck will
+                        // be armed again once the burst settles anyway.
+                        let debouncing =
+                            self.debounce.as_ref().is_some_and(|debounce| {
+                                debounce.pending.is_some()
+                            });
+                        let Some(recheck) =
+                            self.recheck.as_mut().filter(|_| !debouncing)
+                        else {
+                            return std::task::Poll::Pending;
+                        };
+                        futures::ready!(recheck.poll_expired(cx));
+                        self.notified_at = std::time::Instant::now();
+                        rechecking = true;
+                    }
+                }
+            } else {
+                self.notified_at = std::time::Instant::now();
+                self.sent_initial_size = true;
+                initial = true;
+            }
+
+            let size = match self.size() {
+                Err(_) if initial && self.fallback_size => {
+                    Ok(fallback_size())
+                }
+                size => size,
+            };
+            let size = match size {
+                Ok((size, source)) => {
+                    if (self.dedup || rechecking || self.trigger.is_polling())
+                        && self.last_size == Some(size)
+                        && self.source == source
+                    {
+                        continue;
+                    }
+                    self.last_size = Some(size);
+                    self.source = source;
+                    Ok(size)
+                }
+                // a failed recheck isn't worth reporting, since the size
+                // will be checked again on the next notification anyway
+                Err(_) if rechecking || self.skip_errors => continue,
+                Err(Error::ZeroTerminalSize { .. })
+                    if self.zero_size == ZeroSizePolicy::Skip =>
+                {
+                    continue
+                }
+                Err(e) => Err(e),
+            };
+            return std::task::Poll::Ready(Some(size));
+        }
+    }
+}
+
+impl ResizeStream {
+    /// Returns where the most recently returned size came from.
+    ///
+    /// This is always `SizeSource::Terminal` unless
+    /// `ResizeStreamBuilder::fallback_size` or `ZeroSizePolicy::Fallback`
+    /// was used.
+    #[must_use]
+    pub const fn source(&self) -> SizeSource {
+        self.source
+    }
+
+    fn size(&self) -> Result<(TerminalSize, SizeSource), Error> {
+        let (mut size, source) = match (self.target.size(), self.zero_size) {
+            (Ok(size), _) => (size, SizeSource::Terminal),
+            (
+                Err(Error::ZeroTerminalSize { .. }),
+                ZeroSizePolicy::Fallback(size),
+            ) => (size, SizeSource::Default),
+            (Err(e), _) => return Err(e),
+        };
+        if !self.include_pixels {
+            size.pixel_width = None;
+            size.pixel_height = None;
+        }
+        Ok((size, source))
+    }
+
+    // returns true when the terminal size should be checked, and false once
+    // there will be no further notifications
+    fn poll_trigger(
+        &mut self,
+        cx: &mut std::task::Context,
+    ) -> std::task::Poll<bool> {
+        let Some(debounce) = &mut self.debounce else {
+            let notified = futures::ready!(self.trigger.poll_recv(cx));
+            if notified.is_some() {
+                self.notified_at = std::time::Instant::now();
+            }
+            return std::task::Poll::Ready(notified.is_some());
+        };
+
+        loop {
+            match self.trigger.poll_recv(cx) {
+                std::task::Poll::Ready(Some(())) => {
+                    self.notified_at = std::time::Instant::now();
+                    debounce.notify();
+                }
+                std::task::Poll::Ready(None) => {
+                    return std::task::Poll::Ready(
+                        debounce.pending.take().is_some(),
+                    );
+                }
+                std::task::Poll::Pending => break,
+            }
+        }
+        debounce.poll_expired(cx).map(|()| true)
+    }
+}
+
+struct Debounce {
+    delay: std::time::Duration,
+    max_delay: std::time::Duration,
+    pending: Option<(
+        std::pin::Pin<Box<tokio::time::Sleep>>,
+        tokio::time::Instant,
+    )>,
+}
+
+impl Debounce {
+    const fn new(
+        delay: std::time::Duration,
+        max_delay: std::time::Duration,
+    ) -> Self {
+        Self {
+            delay,
+            max_delay,
+            pending: None,
+        }
+    }
+
+    fn notify(&mut self) {
+        let now = tokio::time::Instant::now();
+        if let Some((sleep, max_deadline)) = &mut self.pending {
+            sleep.as_mut().reset((now + self.delay).min(*max_deadline));
+        } else {
+            let max_deadline = now + self.max_delay;
+            self.pending = Some((
+                Box::pin(tokio::time::sleep_until(
+                    (now + self.delay).min(max_deadline),
+                )),
+                max_deadline,
+            ));
+        }
+    }
+
+    fn poll_expired(
+        &mut self,
+        cx: &mut std::task::Context,
+    ) -> std::task::Poll<()> {
+        if let Some((sleep, _)) = &mut self.pending {
+            futures::ready!(sleep.poll_unpin(cx));
+            self.pending = None;
+            std::task::Poll::Ready(())
+        } else {
+            std::task::Poll::Pending
+        }
+    }
+}
+
+struct Recheck {
+    delay: std::time::Duration,
+    sleep: Option<std::pin::Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl Recheck {
+    const fn new(delay: std::time::Duration) -> Self {
+        Self { delay, sleep: None }
+    }
+
+    fn arm(&mut self) {
+        let deadline = tokio::time::Instant::now() + self.delay;
+        if let Some(sleep) = &mut self.sleep {
+            sleep.as_mut().reset(deadline);
+        } else {
+            self.sleep = Some(Box::pin(tokio::time::sleep_until(deadline)));
+        }
+    }
+
+    fn poll_expired(
+        &mut self,
+        cx: &mut std::task::Context,
+    ) -> std::task::Poll<()> {
+        if let Some(sleep) = &mut self.sleep {
+            futures::ready!(sleep.poll_unpin(cx));
+            self.sleep = None;
+            std::task::Poll::Ready(())
+        } else {
+            std::task::Poll::Pending
+        }
+    }
+}
+
+enum Trigger {
+    #[cfg(unix)]
+    Signal(tokio::signal::unix::Signal),
+    #[cfg(any(unix, feature = "test-support"))]
+    Channel(tokio::sync::mpsc::UnboundedReceiver<()>),
+    Poll(tokio::time::Interval),
+}
+
+// tokio panics on a zero interval, and polling more often than this would
+// just be a busy loop anyway
+const MIN_POLL_INTERVAL: std::time::Duration =
+    std::time::Duration::from_millis(1);
+
+impl Trigger {
+    fn poll(interval: std::time::Duration) -> Self {
+        let mut interval =
+            tokio::time::interval(interval.max(MIN_POLL_INTERVAL));
+        interval
+            .set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        Self::Poll(interval)
+    }
 
-    fn poll(&mut self) -> futures::Poll<Option<Self::Item>, Self::Error> {
-        if !self.sent_initial_size {
-            self.sent_initial_size = true;
-            return Ok(futures::Async::Ready(Some(term_size()?)));
+    fn poll_recv(
+        &mut self,
+        cx: &mut std::task::Context,
+    ) -> std::task::Poll<Option<()>> {
+        match self {
+            #[cfg(unix)]
+            Self::Signal(signal) => signal.poll_recv(cx),
+            #[cfg(any(unix, feature = "test-support"))]
+            Self::Channel(rx) => rx.poll_recv(cx),
+            Self::Poll(interval) => interval.poll_tick(cx).map(|_| Some(())),
         }
-        futures::try_ready!(self.winches.poll());
-        Ok(futures::Async::Ready(Some(term_size()?)))
+    }
+
+    // polling triggers fire whether or not anything changed, so they always
+    // need to be deduplicated
+    const fn is_polling(&self) -> bool {
+        match self {
+            #[cfg(unix)]
+            Self::Signal(_) => false,
+            #[cfg(any(unix, feature = "test-support"))]
+            Self::Channel(_) => false,
+            Self::Poll(_) => true,
+        }
+    }
+}
+
+// used by handles which notify their streams of resizes directly, rather
+// than via a signal
+#[cfg(any(unix, feature = "test-support"))]
+#[derive(Default)]
+struct Notifiers {
+    senders: std::sync::Mutex<Vec<tokio::sync::mpsc::UnboundedSender<()>>>,
+}
+
+#[cfg(any(unix, feature = "test-support"))]
+impl Notifiers {
+    fn notify(&self) {
+        self.senders().retain(|sender| sender.send(()).is_ok());
+    }
+
+    fn trigger(&self) -> Trigger {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.senders().push(tx);
+        Trigger::Channel(rx)
+    }
+
+    fn senders(
+        &self,
+    ) -> std::sync::MutexGuard<'_, Vec<tokio::sync::mpsc::UnboundedSender<()>>>
+    {
+        self.senders
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
     }
 }
 
-fn term_size() -> Result<(u16, u16), Error> {
-    if let Some((cols, rows)) = term_size::dimensions() {
-        Ok((
-            rows.try_into().context(InvalidTerminalSize)?,
-            cols.try_into().context(InvalidTerminalSize)?,
-        ))
-    } else {
-        Err(Error::GetTerminalSize)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn console_window_size() {
+        assert_eq!(
+            TerminalSize::from_console_window(0, 0, 79, 23),
+            TerminalSize::new(24, 80)
+        );
+        assert_eq!(
+            TerminalSize::from_console_window(0, 100, 119, 129),
+            TerminalSize::new(30, 120)
+        );
+        assert_eq!(
+            TerminalSize::from_console_window(0, 0, 0, 0),
+            TerminalSize::new(1, 1)
+        );
+        assert_eq!(
+            TerminalSize::from_console_window(0, 0, -1, 23),
+            TerminalSize::new(24, 0)
+        );
+        assert_eq!(
+            TerminalSize::from_console_window(0, 23, 79, 0),
+            TerminalSize::new(0, 80)
+        );
+    }
+
+    #[test]
+    fn size_from_env() {
+        assert_eq!(env_size(None, None), None);
+        assert_eq!(
+            env_size(Some("50"), Some("132")),
+            Some(TerminalSize::new(50, 132))
+        );
+        assert_eq!(
+            env_size(None, Some("100")),
+            Some(TerminalSize::new(24, 100))
+        );
+        assert_eq!(
+            env_size(Some("30"), None),
+            Some(TerminalSize::new(30, 80))
+        );
+        assert_eq!(env_size(Some("0"), Some("wide")), None);
+        assert_eq!(env_size(Some(""), Some("-1")), None);
+    }
+
+    #[test]
+    fn check_size() {
+        assert!(TerminalSize::new(24, 80).check(None).is_ok());
+        assert!(matches!(
+            TerminalSize::new(0, 80).check(Some(1)),
+            Err(Error::ZeroTerminalSize {
+                fd: Some(1),
+                rows: 0,
+                cols: 80
+            })
+        ));
+        assert!(matches!(
+            TerminalSize::new(24, 0).check(None),
+            Err(Error::ZeroTerminalSize { .. })
+        ));
     }
 }
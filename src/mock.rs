@@ -0,0 +1,95 @@
+//! Support for testing code which consumes a `ResizeStream`, without
+//! needing a real terminal. Requires the `test-support` feature.
+//!
+//! ```
+//! # use futures::stream::StreamExt as _;
+//! # use tokio_terminal_resize::{mock::MockTerminal, TerminalSize};
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), tokio_terminal_resize::Error> {
+//! let terminal = MockTerminal::new(TerminalSize::new(24, 80));
+//! let mut stream = terminal.resizes();
+//! assert_eq!(stream.next().await.unwrap()?, TerminalSize::new(24, 80));
+//! terminal.resize(TerminalSize::new(50, 132));
+//! assert_eq!(stream.next().await.unwrap()?, TerminalSize::new(50, 132));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Error, ResizeStream, Target, TerminalSize, Trigger};
+
+pub(crate) type SharedSize =
+    std::sync::Arc<std::sync::Mutex<Option<TerminalSize>>>;
+
+/// A fake terminal whose size is controlled programmatically.
+///
+/// Streams created via `resizes` (or `ResizeStreamBuilder::mock`) behave
+/// exactly like streams following a real terminal, except that they are
+/// notified of resizes by calls to `resize` rather than by a signal. The
+/// streams end when the `MockTerminal` is dropped.
+pub struct MockTerminal {
+    size: SharedSize,
+    notifiers: crate::Notifiers,
+}
+
+impl MockTerminal {
+    /// Creates a new fake terminal with the given size.
+    #[must_use]
+    pub fn new(size: TerminalSize) -> Self {
+        Self {
+            size: std::sync::Arc::new(std::sync::Mutex::new(Some(size))),
+            notifiers: crate::Notifiers::default(),
+        }
+    }
+
+    /// Returns the current size of the terminal, or `None` if `detach` has
+    /// been called since the last call to `resize`.
+    #[must_use]
+    pub fn size(&self) -> Option<TerminalSize> {
+        *lock(&self.size)
+    }
+
+    /// Sets the size of the terminal, and notifies all streams following
+    /// this terminal of the change.
+    pub fn resize(&self, size: TerminalSize) {
+        *lock(&self.size) = Some(size);
+        self.notifiers.notify();
+    }
+
+    /// Makes the terminal size unavailable (as if the terminal had been
+    /// closed), and notifies all streams following this terminal. Streams
+    /// will fail to get the terminal size until the next call to `resize`.
+    pub fn detach(&self) {
+        *lock(&self.size) = None;
+        self.notifiers.notify();
+    }
+
+    /// Creates a stream which receives the new size of the terminal every
+    /// time `resize` is called.
+    ///
+    /// To configure the returned stream, use `ResizeStreamBuilder::mock`
+    /// instead.
+    pub fn resizes(&self) -> ResizeStream {
+        ResizeStream::new(Target::Mock(self.shared_size()), self.trigger())
+    }
+
+    pub(crate) fn shared_size(&self) -> SharedSize {
+        self.size.clone()
+    }
+
+    pub(crate) fn trigger(&self) -> Trigger {
+        self.notifiers.trigger()
+    }
+}
+
+pub(crate) fn size(size: &SharedSize) -> Result<TerminalSize, Error> {
+    lock(size)
+        .ok_or(Error::NotATerminal { fd: None })?
+        .check(None)
+}
+
+fn lock(
+    size: &SharedSize,
+) -> std::sync::MutexGuard<'_, Option<TerminalSize>> {
+    size.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
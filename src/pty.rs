@@ -0,0 +1,69 @@
+use crate::{ResizeStream, Target, TerminalSize, Trigger};
+use snafu::ResultExt as _;
+
+/// Handle to the master side of a PTY whose size is controlled by this
+/// process.
+///
+/// SIGWINCH is only delivered to the foreground process group of the PTY's
+/// slave side, so a process managing a PTY (such as a terminal multiplexer)
+/// has no way to be notified when its size changes. Instead, size changes
+/// should be made through `set_size`, which will notify any streams created
+/// via `resizes`.
+pub struct PtyHandle {
+    fd: std::os::unix::io::RawFd,
+    notifiers: crate::Notifiers,
+}
+
+impl PtyHandle {
+    /// Creates a new handle for the PTY master referred to by `master`.
+    ///
+    /// The file descriptor is not owned by the returned handle, so it must
+    /// remain open for as long as the handle (or any stream created from it)
+    /// is in use.
+    pub fn new(master: &impl std::os::unix::io::AsRawFd) -> Self {
+        Self {
+            fd: master.as_raw_fd(),
+            notifiers: crate::Notifiers::default(),
+        }
+    }
+
+    /// Returns the current size of the PTY.
+    ///
+    /// # Errors
+    /// * `Error::GetTerminalSize`: failed to get the size of the PTY
+    /// * `Error::ZeroTerminalSize`: the PTY's size hasn't been set
+    pub fn size(&self) -> Result<TerminalSize, crate::Error> {
+        Target::Fd(self.fd).size()
+    }
+
+    /// Sets the size of the PTY, and notifies all streams created via
+    /// `resizes` of the change.
+    ///
+    /// # Errors
+    /// * `Error::SetTerminalSize`: failed to set the size of the PTY
+    pub fn set_size(&self, size: TerminalSize) -> Result<(), crate::Error> {
+        crate::set_winsize(self.fd, size).context(crate::SetTerminalSize)?;
+        self.notifiers.notify();
+        Ok(())
+    }
+
+    /// Creates a stream which receives the new size of the PTY every time
+    /// `set_size` is called.
+    ///
+    /// Like the stream returned by `resizes`, this first receives the
+    /// current size of the PTY.
+    ///
+    /// To configure the returned stream, use `ResizeStreamBuilder::pty`
+    /// instead.
+    pub fn resizes(&self) -> ResizeStream {
+        ResizeStream::new(Target::Fd(self.fd), self.trigger())
+    }
+
+    pub(crate) const fn fd(&self) -> std::os::unix::io::RawFd {
+        self.fd
+    }
+
+    pub(crate) fn trigger(&self) -> Trigger {
+        self.notifiers.trigger()
+    }
+}
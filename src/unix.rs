@@ -0,0 +1,114 @@
+use crate::{Error, TerminalSize, Trigger};
+use snafu::ResultExt as _;
+
+pub enum Target {
+    Stdio,
+    Fd(std::os::unix::io::RawFd),
+    File(std::fs::File),
+    #[cfg(feature = "test-support")]
+    Mock(crate::mock::SharedSize),
+}
+
+impl Target {
+    pub fn size(&self) -> Result<TerminalSize, Error> {
+        match self {
+            Self::Stdio => term_size(&[
+                libc::STDOUT_FILENO,
+                libc::STDIN_FILENO,
+                libc::STDERR_FILENO,
+            ]),
+            Self::Fd(fd) => term_size(&[*fd]),
+            Self::File(file) => {
+                term_size(&[std::os::unix::io::AsRawFd::as_raw_fd(file)])
+            }
+            #[cfg(feature = "test-support")]
+            Self::Mock(size) => crate::mock::size(size),
+        }
+    }
+}
+
+pub fn default_trigger() -> Result<Trigger, Error> {
+    Ok(Trigger::Signal(
+        tokio::signal::unix::signal(
+            tokio::signal::unix::SignalKind::window_change(),
+        )
+        .context(crate::SigWinchHandler)?,
+    ))
+}
+
+// always refers to the controlling terminal of the process which opens it
+pub const CONTROLLING_TERMINAL: &str = "/dev/tty";
+
+pub fn open_terminal(path: &std::path::Path) -> Result<std::fs::File, Error> {
+    std::os::unix::fs::OpenOptionsExt::custom_flags(
+        std::fs::OpenOptions::new().read(true),
+        libc::O_NOCTTY,
+    )
+    .open(path)
+    .context(crate::OpenTerminal { path })
+}
+
+pub fn set_winsize(
+    fd: std::os::unix::io::RawFd,
+    size: TerminalSize,
+) -> std::io::Result<()> {
+    let ws = size.to_winsize();
+    // safe because ws is a valid winsize struct which outlives the call
+    let res = unsafe { libc::ioctl(fd, libc::TIOCSWINSZ, &ws) };
+    if res == -1 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+impl TerminalSize {
+    fn from_winsize(ws: libc::winsize) -> Self {
+        let pixels = |n| if n == 0 { None } else { Some(n) };
+        Self {
+            rows: ws.ws_row,
+            cols: ws.ws_col,
+            pixel_width: pixels(ws.ws_xpixel),
+            pixel_height: pixels(ws.ws_ypixel),
+        }
+    }
+
+    fn to_winsize(self) -> libc::winsize {
+        libc::winsize {
+            ws_row: self.rows,
+            ws_col: self.cols,
+            ws_xpixel: self.pixel_width.unwrap_or(0),
+            ws_ypixel: self.pixel_height.unwrap_or(0),
+        }
+    }
+}
+
+fn term_size(
+    fds: &[std::os::unix::io::RawFd],
+) -> Result<TerminalSize, Error> {
+    let (fd, ws) = crate::first_ok(
+        fds.iter().map(|&fd| get_winsize(fd).map(|ws| (fd, ws))),
+    )?;
+    TerminalSize::from_winsize(ws).check(Some(fd))
+}
+
+fn get_winsize(fd: std::os::unix::io::RawFd) -> Result<libc::winsize, Error> {
+    let mut ws = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // safe because ws is a valid winsize struct which outlives the call
+    let res = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut ws) };
+    if res == -1 {
+        let e = std::io::Error::last_os_error();
+        if e.raw_os_error() == Some(libc::ENOTTY) {
+            Err(Error::NotATerminal { fd: Some(fd) })
+        } else {
+            Err(e).context(crate::GetTerminalSize { fd: Some(fd) })
+        }
+    } else {
+        Ok(ws)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{Error, TerminalSize, Trigger};
+use snafu::ResultExt as _;
+use windows_sys::Win32::Foundation;
+use windows_sys::Win32::System::Console;
+
+// the windows console has no equivalent of SIGWINCH which doesn't involve
+// consuming events from the console input buffer (which would interfere with
+// the application reading its own input), so we poll for changes instead
+const POLL_INTERVAL: std::time::Duration =
+    std::time::Duration::from_millis(100);
+
+pub enum Target {
+    Stdio,
+    #[cfg(feature = "test-support")]
+    Mock(crate::mock::SharedSize),
+}
+
+impl Target {
+    pub fn size(&self) -> Result<TerminalSize, Error> {
+        match self {
+            // the console handles are reported using the equivalent c
+            // runtime file descriptors, to match the unix implementation
+            Self::Stdio => console_size(&[
+                (Console::STD_OUTPUT_HANDLE, 1),
+                (Console::STD_ERROR_HANDLE, 2),
+            ]),
+            #[cfg(feature = "test-support")]
+            Self::Mock(size) => crate::mock::size(size),
+        }
+    }
+}
+
+// this can't fail, but the unix implementation can
+#[allow(clippy::unnecessary_wraps)]
+pub fn default_trigger() -> Result<Trigger, Error> {
+    Ok(Trigger::poll(POLL_INTERVAL))
+}
+
+fn console_size(
+    handles: &[(Console::STD_HANDLE, std::os::raw::c_int)],
+) -> Result<TerminalSize, Error> {
+    let (fd, info) = crate::first_ok(handles.iter().map(|&(handle, fd)| {
+        get_screen_buffer_info(handle, fd).map(|info| (fd, info))
+    }))?;
+    let window = info.srWindow;
+    TerminalSize::from_console_window(
+        window.Left,
+        window.Top,
+        window.Right,
+        window.Bottom,
+    )
+    .check(Some(fd))
+}
+
+fn get_screen_buffer_info(
+    handle: Console::STD_HANDLE,
+    fd: std::os::raw::c_int,
+) -> Result<Console::CONSOLE_SCREEN_BUFFER_INFO, Error> {
+    // safe because GetStdHandle has no preconditions
+    let handle = unsafe { Console::GetStdHandle(handle) };
+    // safe because CONSOLE_SCREEN_BUFFER_INFO is a plain c struct, for which
+    // all zeroes is a valid value
+    let mut info: Console::CONSOLE_SCREEN_BUFFER_INFO =
+        unsafe { std::mem::zeroed() };
+    // safe because info is a valid CONSOLE_SCREEN_BUFFER_INFO struct which
+    // outlives the call, and invalid handles are reported as errors
+    let res = unsafe {
+        Console::GetConsoleScreenBufferInfo(
+            handle,
+            std::ptr::addr_of_mut!(info),
+        )
+    };
+    if res == 0 {
+        let e = std::io::Error::last_os_error();
+        if e.raw_os_error()
+            .and_then(|code| std::convert::TryFrom::try_from(code).ok())
+            == Some(Foundation::ERROR_INVALID_HANDLE)
+        {
+            Err(Error::NotATerminal { fd: Some(fd) })
+        } else {
+            Err(e).context(crate::GetTerminalSize { fd: Some(fd) })
+        }
+    } else {
+        Ok(info)
+    }
+}
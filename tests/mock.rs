@@ -0,0 +1,246 @@
+// these tests require the test-support feature, and so are only run by
+// `cargo test --features test-support` (or `--all-features`)
+
+use futures::future::FutureExt as _;
+use futures::stream::StreamExt as _;
+use tokio_terminal_resize::{
+    mock::MockTerminal, ResizeHub, ResizeStream, TerminalSize,
+};
+
+#[tokio::test]
+async fn events_after_initial_size() {
+    let terminal = MockTerminal::new(TerminalSize::new(24, 80));
+    let mut events = ResizeStream::builder()
+        .mock(&terminal)
+        .initial_size(false)
+        .build()
+        .unwrap()
+        .events();
+    terminal.resize(TerminalSize::new(30, 100));
+    let event = events.next().await.unwrap().unwrap();
+    assert_eq!(event.old, Some(TerminalSize::new(24, 80)));
+    assert_eq!(event.new, TerminalSize::new(30, 100));
+    assert!(!event.is_initial());
+    assert!(event.grew_rows());
+    assert!(event.grew_cols());
+
+    // converting a stream which has already returned a size
+    let mut stream = terminal.resizes();
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        TerminalSize::new(30, 100)
+    );
+    let mut events = stream.events();
+    terminal.resize(TerminalSize::new(24, 80));
+    let event = events.next().await.unwrap().unwrap();
+    assert_eq!(event.old, Some(TerminalSize::new(30, 100)));
+    assert!(!event.is_initial());
+    assert!(event.shrank_rows());
+    assert!(event.shrank_cols());
+}
+
+#[tokio::test(start_paused = true)]
+async fn debounce_returns_settled_size() {
+    let delay = std::time::Duration::from_millis(100);
+    let terminal = MockTerminal::new(TerminalSize::new(24, 80));
+    let mut stream = ResizeStream::builder()
+        .mock(&terminal)
+        .debounce(delay, std::time::Duration::from_secs(1))
+        .build()
+        .unwrap();
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        TerminalSize::new(24, 80)
+    );
+
+    let start = tokio::time::Instant::now();
+    for cols in 81..=83 {
+        terminal.resize(TerminalSize::new(24, cols));
+        assert!(futures::poll!(stream.next()).is_pending());
+        tokio::time::sleep(delay / 2).await;
+    }
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        TerminalSize::new(24, 83)
+    );
+    assert_eq!(start.elapsed(), delay * 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn debounce_max_delay() {
+    let delay = std::time::Duration::from_millis(100);
+    let max_delay = std::time::Duration::from_millis(250);
+    let terminal = MockTerminal::new(TerminalSize::new(24, 80));
+    let mut stream = ResizeStream::builder()
+        .mock(&terminal)
+        .debounce(delay, max_delay)
+        .build()
+        .unwrap();
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        TerminalSize::new(24, 80)
+    );
+
+    // resize more often than delay, so that the burst never settles
+    let start = tokio::time::Instant::now();
+    let mut cols = 80;
+    let size = loop {
+        cols += 1;
+        terminal.resize(TerminalSize::new(24, cols));
+        if let std::task::Poll::Ready(size) = futures::poll!(stream.next()) {
+            break size.unwrap().unwrap();
+        }
+        assert!(start.elapsed() < max_delay);
+        tokio::time::sleep(delay / 2).await;
+    };
+    assert_eq!(start.elapsed(), max_delay);
+    assert_eq!(size, TerminalSize::new(24, cols));
+}
+
+#[tokio::test(start_paused = true)]
+async fn debounce_flushes_when_trigger_ends() {
+    let terminal = MockTerminal::new(TerminalSize::new(24, 80));
+    let mut stream = ResizeStream::builder()
+        .mock(&terminal)
+        .debounce(
+            std::time::Duration::from_millis(100),
+            std::time::Duration::from_secs(1),
+        )
+        .build()
+        .unwrap();
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        TerminalSize::new(24, 80)
+    );
+
+    terminal.resize(TerminalSize::new(30, 100));
+    assert!(futures::poll!(stream.next()).is_pending());
+    let start = tokio::time::Instant::now();
+    drop(terminal);
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        TerminalSize::new(30, 100)
+    );
+    assert_eq!(start.elapsed(), std::time::Duration::ZERO);
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn event_seq_and_time() {
+    let terminal = MockTerminal::new(TerminalSize::new(24, 80));
+    let mut events = terminal.resizes().events();
+    let mut last_time = None;
+    for (seq, cols) in (0..).zip(80..85) {
+        if seq > 0 {
+            terminal.resize(TerminalSize::new(24, cols));
+        }
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.seq, seq);
+        assert!(last_time.is_none_or(|time| event.time >= time));
+        last_time = Some(event.time);
+    }
+}
+
+#[tokio::test]
+async fn size_watch() {
+    let terminal = MockTerminal::new(TerminalSize::new(24, 80));
+    let hub = ResizeHub::new(terminal.resizes());
+    let mut early = hub.watch();
+    assert_eq!(early.changed().await, Some(TerminalSize::new(24, 80)));
+    assert_eq!(early.get(), Some(TerminalSize::new(24, 80)));
+
+    // sizes received before the handle was created don't count as changes
+    let mut late = hub.watch();
+    assert_eq!(late.get(), Some(TerminalSize::new(24, 80)));
+    assert!(late.changed().now_or_never().is_none());
+
+    terminal.resize(TerminalSize::new(30, 100));
+    assert_eq!(late.changed().await, Some(TerminalSize::new(30, 100)));
+    assert_eq!(early.get(), Some(TerminalSize::new(30, 100)));
+
+    drop(terminal);
+    assert_eq!(late.changed().await, None);
+}
+
+#[tokio::test]
+async fn resize_hub() {
+    let terminal = MockTerminal::new(TerminalSize::new(24, 80));
+    let hub = ResizeHub::new(terminal.resizes());
+    let mut first = hub.subscribe();
+    let mut second = hub.subscribe();
+    assert_eq!(first.next().await, Some(TerminalSize::new(24, 80)));
+    assert_eq!(second.next().await, Some(TerminalSize::new(24, 80)));
+
+    terminal.resize(TerminalSize::new(30, 100));
+    assert_eq!(first.next().await, Some(TerminalSize::new(30, 100)));
+    assert_eq!(second.next().await, Some(TerminalSize::new(30, 100)));
+
+    // late subscribers start from the latest size
+    let mut late = hub.subscribe();
+    assert_eq!(late.next().await, Some(TerminalSize::new(30, 100)));
+
+    // errors are skipped
+    terminal.detach();
+    settle().await;
+    assert!(first.next().now_or_never().is_none());
+    terminal.resize(TerminalSize::new(50, 132));
+    assert_eq!(first.next().await, Some(TerminalSize::new(50, 132)));
+    assert_eq!(late.next().await, Some(TerminalSize::new(50, 132)));
+
+    // the background task exits once nothing can receive sizes from it,
+    // even though the terminal is still alive
+    let metrics = tokio::runtime::Handle::current().metrics();
+    assert_eq!(metrics.num_alive_tasks(), 1);
+    drop(hub);
+    drop(first);
+    drop(second);
+    settle().await;
+    assert_eq!(metrics.num_alive_tasks(), 1);
+    drop(late);
+    settle().await;
+    assert_eq!(metrics.num_alive_tasks(), 0);
+}
+
+// lets any tasks spawned onto the current thread runtime run until they
+// are blocked
+async fn settle() {
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn debounce_with_recheck() {
+    let delay = std::time::Duration::from_millis(100);
+    let terminal = MockTerminal::new(TerminalSize::new(24, 80));
+    let mut stream = ResizeStream::builder()
+        .mock(&terminal)
+        .debounce(delay, std::time::Duration::from_secs(1))
+        .recheck_after(delay / 2)
+        .build()
+        .unwrap();
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        TerminalSize::new(24, 80)
+    );
+
+    terminal.resize(TerminalSize::new(24, 81));
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        TerminalSize::new(24, 81)
+    );
+
+    // the recheck armed by the previous size expires during this burst, but
+    // shouldn't return the size from the middle of it
+    let start = tokio::time::Instant::now();
+    for cols in 82..=84 {
+        terminal.resize(TerminalSize::new(24, cols));
+        assert!(futures::poll!(stream.next()).is_pending());
+        tokio::time::sleep(delay / 2).await;
+    }
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        TerminalSize::new(24, 84)
+    );
+    assert_eq!(start.elapsed(), delay * 2);
+}
@@ -0,0 +1,415 @@
+#![cfg(unix)]
+
+use futures::stream::StreamExt as _;
+use std::io::BufRead as _;
+use std::os::unix::io::{AsRawFd as _, FromRawFd as _};
+use std::os::unix::process::CommandExt as _;
+use tokio_terminal_resize::{
+    Error, PtyHandle, ResizeStream, SizeSource, TerminalSize, ZeroSizePolicy,
+};
+
+const CHILD_ENV: &str = "TOKIO_TERMINAL_RESIZE_TEST_CHILD";
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+struct Pty {
+    master: std::fs::File,
+    slave: std::fs::File,
+}
+
+impl Pty {
+    fn new(rows: u16, cols: u16) -> Self {
+        let mut master = 0;
+        let mut slave = 0;
+        let res = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(res, 0, "{}", std::io::Error::last_os_error());
+        let pty = unsafe {
+            Self {
+                master: std::fs::File::from_raw_fd(master),
+                slave: std::fs::File::from_raw_fd(slave),
+            }
+        };
+        pty.resize(rows, cols);
+        pty
+    }
+
+    fn resize(&self, rows: u16, cols: u16) {
+        let ws = libc::winsize {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let res = unsafe {
+            libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &ws)
+        };
+        assert_eq!(res, 0, "{}", std::io::Error::last_os_error());
+    }
+
+    fn size(file: &std::fs::File) -> (u16, u16) {
+        let mut ws = libc::winsize {
+            ws_row: 0,
+            ws_col: 0,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let res = unsafe {
+            libc::ioctl(file.as_raw_fd(), libc::TIOCGWINSZ, &mut ws)
+        };
+        assert_eq!(res, 0, "{}", std::io::Error::last_os_error());
+        (ws.ws_row, ws.ws_col)
+    }
+}
+
+async fn next_size(stream: &mut ResizeStream) -> TerminalSize {
+    tokio::time::timeout(TIMEOUT, stream.next())
+        .await
+        .expect("timed out waiting for resize")
+        .expect("stream ended")
+        .expect("failed to get terminal size")
+}
+
+// runs in a child process whose controlling terminal is the pty created by
+// spawn_child, and reports each size it receives
+#[tokio::test]
+async fn child_report_resizes() {
+    let Some(mode) = std::env::var_os(CHILD_ENV) else {
+        return;
+    };
+    let mut stream = if mode == "tty" {
+        tokio_terminal_resize::resizes_for_controlling_terminal().unwrap()
+    } else {
+        tokio_terminal_resize::resizes().unwrap()
+    };
+    for _ in 0..3 {
+        let size = next_size(&mut stream).await;
+        println!("size {} {}", size.rows, size.cols);
+    }
+}
+
+// runs child_report_resizes with pty as its controlling terminal, and
+// returns a channel which receives each size it reports. if stdio is false,
+// the child's stdio is not connected to the pty at all.
+fn spawn_child(
+    pty: &Pty,
+    stdio: bool,
+) -> (std::process::Child, std::sync::mpsc::Receiver<String>) {
+    let slave_fd = pty.slave.as_raw_fd();
+    let mut cmd =
+        std::process::Command::new(std::env::current_exe().unwrap());
+    cmd.args(["child_report_resizes", "--exact", "--nocapture"]);
+    if stdio {
+        cmd.env(CHILD_ENV, "stdio")
+            .stdin(pty.slave.try_clone().unwrap())
+            .stdout(pty.slave.try_clone().unwrap())
+            .stderr(pty.slave.try_clone().unwrap());
+    } else {
+        cmd.env(CHILD_ENV, "tty")
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null());
+    }
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if libc::ioctl(slave_fd, libc::TIOCSCTTY, 0) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut child = cmd.spawn().unwrap();
+    drop(cmd);
+
+    let output: Box<dyn std::io::Read + Send> = match child.stdout.take() {
+        Some(stdout) => Box::new(stdout),
+        None => Box::new(pty.master.try_clone().unwrap()),
+    };
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(output).lines() {
+            // reading from the master fails with EIO once the child exits
+            let Ok(line) = line else { break };
+            // libtest prints the test name on the same line as the first
+            // line of output
+            if let Some((_, size)) = line.trim().split_once("size ") {
+                tx.send(size.to_string()).unwrap();
+            }
+        }
+    });
+
+    (child, rx)
+}
+
+#[test]
+fn resizes_follow_controlling_terminal() {
+    let pty = Pty::new(24, 80);
+    let (mut child, rx) = spawn_child(&pty, true);
+
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), "24 80");
+    pty.resize(30, 100);
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), "30 100");
+    pty.resize(50, 132);
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), "50 132");
+
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn resizes_for_controlling_terminal() {
+    let pty = Pty::new(24, 80);
+    let (mut child, rx) = spawn_child(&pty, false);
+
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), "24 80");
+    pty.resize(30, 100);
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), "30 100");
+    pty.resize(50, 132);
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), "50 132");
+
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn current_size_for_fd() {
+    let pty = Pty::new(24, 80);
+    let size =
+        tokio_terminal_resize::current_size_for_fd(&pty.slave).unwrap();
+    assert_eq!(size, TerminalSize::new(24, 80));
+    pty.resize(30, 100);
+    let size =
+        tokio_terminal_resize::current_size_for_fd(&pty.slave).unwrap();
+    assert_eq!(size, TerminalSize::new(30, 100));
+}
+
+#[test]
+fn current_size_errors() {
+    let exe = std::env::current_exe().unwrap();
+    let file = std::fs::File::open(exe).unwrap();
+    let err = tokio_terminal_resize::current_size_for_fd(&file).unwrap_err();
+    assert!(err.is_not_a_terminal());
+    assert!(matches!(
+        err,
+        Error::NotATerminal { fd: Some(fd) }
+            if fd == file.as_raw_fd()
+    ));
+
+    // a newly allocated pty has no size until one is set
+    let pty = Pty::new(0, 0);
+    let err =
+        tokio_terminal_resize::current_size_for_fd(&pty.slave).unwrap_err();
+    assert!(!err.is_not_a_terminal());
+    assert!(matches!(
+        err,
+        Error::ZeroTerminalSize {
+            rows: 0,
+            cols: 0,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn pty_handle_resizes() {
+    let pty = Pty::new(24, 80);
+    let handle = PtyHandle::new(&pty.master);
+    let mut stream = handle.resizes();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(24, 80));
+
+    handle.set_size(TerminalSize::new(30, 100)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(30, 100));
+    assert_eq!(Pty::size(&pty.slave), (30, 100));
+
+    // duplicate sizes are skipped by default
+    handle.set_size(TerminalSize::new(30, 100)).unwrap();
+    handle.set_size(TerminalSize::new(50, 132)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(50, 132));
+
+    drop(handle);
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn pty_handle_skip_initial_size() {
+    let pty = Pty::new(24, 80);
+    let handle = PtyHandle::new(&pty.master);
+    let mut stream = ResizeStream::builder()
+        .pty(&handle)
+        .initial_size(false)
+        .build()
+        .unwrap();
+    assert!(futures::poll!(stream.next()).is_pending());
+
+    // the size at build time is still used for dedup
+    handle.set_size(TerminalSize::new(24, 80)).unwrap();
+    assert!(futures::poll!(stream.next()).is_pending());
+
+    handle.set_size(TerminalSize::new(30, 100)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(30, 100));
+    assert!(futures::poll!(stream.next()).is_pending());
+}
+
+#[tokio::test(start_paused = true)]
+async fn recheck_after() {
+    let delay = std::time::Duration::from_millis(100);
+    let pty = Pty::new(24, 80);
+    let handle = PtyHandle::new(&pty.master);
+    let mut stream = ResizeStream::builder()
+        .pty(&handle)
+        .recheck_after(delay)
+        // rechecks never return duplicate sizes, regardless of dedup
+        .dedup(false)
+        .build()
+        .unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(24, 80));
+
+    handle.set_size(TerminalSize::new(30, 100)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(30, 100));
+    // resizing the pty directly doesn't notify the handle's streams
+    let start = tokio::time::Instant::now();
+    pty.resize(50, 132);
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(50, 132));
+    assert_eq!(start.elapsed(), delay);
+
+    // a recheck which finds the same size returns nothing
+    handle.set_size(TerminalSize::new(24, 80)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(24, 80));
+    tokio::time::sleep(delay * 2).await;
+    assert!(futures::poll!(stream.next()).is_pending());
+}
+
+#[tokio::test]
+async fn zero_size_policy() {
+    let pty = Pty::new(0, 0);
+    let handle = PtyHandle::new(&pty.master);
+    let mut stream = handle.resizes();
+    let err = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(err, Error::ZeroTerminalSize { .. }));
+    handle.set_size(TerminalSize::new(24, 80)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(24, 80));
+
+    pty.resize(0, 0);
+    let mut stream = ResizeStream::builder()
+        .pty(&handle)
+        .zero_size(ZeroSizePolicy::Skip)
+        .build()
+        .unwrap();
+    assert!(futures::poll!(stream.next()).is_pending());
+    handle.set_size(TerminalSize::new(0, 80)).unwrap();
+    assert!(futures::poll!(stream.next()).is_pending());
+    handle.set_size(TerminalSize::new(30, 100)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(30, 100));
+
+    pty.resize(0, 0);
+    let fallback = TerminalSize::new(24, 80);
+    let mut stream = ResizeStream::builder()
+        .pty(&handle)
+        .zero_size(ZeroSizePolicy::Fallback(fallback))
+        .build()
+        .unwrap();
+    assert_eq!(next_size(&mut stream).await, fallback);
+    handle.set_size(TerminalSize::new(30, 100)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(30, 100));
+}
+
+#[tokio::test]
+async fn skip_errors() {
+    let pty = Pty::new(24, 80);
+    let handle = PtyHandle::new(&pty.master);
+    let mut stream = ResizeStream::builder()
+        .pty(&handle)
+        .skip_errors(true)
+        .build()
+        .unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(24, 80));
+
+    handle.set_size(TerminalSize::new(0, 0)).unwrap();
+    assert!(futures::poll!(stream.next()).is_pending());
+    handle.set_size(TerminalSize::new(30, 100)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(30, 100));
+}
+
+#[tokio::test]
+async fn fallback_size() {
+    let pty = Pty::new(0, 0);
+    let handle = PtyHandle::new(&pty.master);
+    let mut stream = ResizeStream::builder()
+        .pty(&handle)
+        .fallback_size(true)
+        .build()
+        .unwrap();
+    next_size(&mut stream).await;
+    assert_ne!(stream.source(), SizeSource::Terminal);
+
+    handle.set_size(TerminalSize::new(30, 100)).unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(30, 100));
+    assert_eq!(stream.source(), SizeSource::Terminal);
+
+    // only the initial size falls back
+    handle.set_size(TerminalSize::new(0, 0)).unwrap();
+    let err = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(err, Error::ZeroTerminalSize { .. }));
+}
+
+#[tokio::test]
+async fn forward_to() {
+    let outer = Pty::new(24, 80);
+    let inner = Pty::new(10, 10);
+    let handle = PtyHandle::new(&outer.master);
+    let forward = tokio::spawn(
+        handle.resizes().forward_to(vec![inner.master.as_raw_fd()]),
+    );
+
+    let deadline = std::time::Instant::now() + TIMEOUT;
+    while Pty::size(&inner.slave) != (24, 80) {
+        assert!(std::time::Instant::now() < deadline);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    // failing to get the size doesn't stop forwarding
+    handle.set_size(TerminalSize::new(0, 0)).unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert!(!forward.is_finished());
+    assert_eq!(Pty::size(&inner.slave), (24, 80));
+
+    handle.set_size(TerminalSize::new(30, 100)).unwrap();
+    while Pty::size(&inner.slave) != (30, 100) {
+        assert!(std::time::Instant::now() < deadline);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    drop(handle);
+    forward.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn poll_interval() {
+    let pty = Pty::new(24, 80);
+    let mut stream = ResizeStream::builder()
+        .fd(&pty.slave)
+        .poll_interval(std::time::Duration::from_millis(10))
+        .build()
+        .unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(24, 80));
+
+    pty.resize(30, 100);
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(30, 100));
+
+    // a zero interval doesn't make tokio panic
+    let mut stream = ResizeStream::builder()
+        .fd(&pty.slave)
+        .poll_interval(std::time::Duration::ZERO)
+        .build()
+        .unwrap();
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(30, 100));
+    pty.resize(50, 132);
+    assert_eq!(next_size(&mut stream).await, TerminalSize::new(50, 132));
+}